@@ -24,16 +24,17 @@
 // SUCH DAMAGE.
 //
 
-#[macro_use(err, errp)]
+#[macro_use(errc, errpc)]
 extern crate unixcli;
 
 use std::env;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, Read, Write};
+use std::io::Read;
 use std::path::Path;
 
+use unixcli::io::fastcopy;
 use unixcli::progname;
 
 fn main() {
@@ -48,9 +49,9 @@ fn main() {
         let path = Path::new(path.as_os_str());
         if let Err(e) = cat_one(path) {
             if path == Path::new("-") {
-                err!(1, "stdin: {}", e);
+                errc!(1, e, "stdin");
             } else {
-                errp!(1, path, "{}", e);
+                errpc!(1, path, e);
             }
         }
     }
@@ -64,13 +65,7 @@ fn cat_one(path: &Path) -> io::Result<()> {
     }
 }
 
-fn cat_read<R>(r: R) -> io::Result<()> where R: Read {
-    let mut reader = BufReader::new(r);
-    let mut buf = vec![0; 64 * 1024];
-    loop {
-        match try!(reader.read(&mut buf)) {
-            0 => return Ok(()),
-            n => { try!(io::stdout().write(&buf[0..n])); },
-        }
-    }
+fn cat_read<R>(mut r: R) -> io::Result<()> where R: Read {
+    try!(fastcopy(&mut r, &mut io::stdout()));
+    Ok(())
 }
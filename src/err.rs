@@ -26,15 +26,135 @@
 
 //! Prints messages to the standard error output.
 
+use std::env;
 use std::fmt;
+use std::io;
 use std::io::Write;
+use std::mem;
 #[cfg(unix)]
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::str;
+use std::sync::{Mutex, Once, ONCE_INIT};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 
 use progname;
 
+/// Controls when diagnostic messages are colorized with ANSI escapes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colorize only when stderr is an interactive terminal and the
+    /// `NO_COLOR` environment variable is unset.  This is the default.
+    Auto,
+    /// Always colorize, regardless of whether stderr is a terminal.
+    Always,
+    /// Never colorize.
+    Never,
+}
+
+static COLOR: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Sets when `err!`/`warn!` and their variants colorize their output.
+/// The default is `ColorChoice::Auto`.
+pub fn set_color(choice: ColorChoice) {
+    COLOR.store(choice as usize, Ordering::Relaxed);
+}
+
+fn color_choice() -> ColorChoice {
+    match COLOR.load(Ordering::Relaxed) {
+        1 => ColorChoice::Always,
+        2 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+fn use_color() -> bool {
+    match color_choice() {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => env::var_os("NO_COLOR").is_none() && stderr_is_tty(),
+    }
+}
+
+#[cfg(unix)]
+fn stderr_is_tty() -> bool {
+    extern "C" {
+        fn isatty(fd: i32) -> i32;
+    }
+    unsafe { isatty(2) != 0 }
+}
+
+#[cfg(not(unix))]
+fn stderr_is_tty() -> bool { false }
+
+/// Severity levels for diagnostic messages, modeled on the kernel's
+/// `printk` levels: earlier variants are more severe.  `err!`, `errp!`,
+/// `errc!`, and `errpc!` are always at `Level::Error` and, being the
+/// most severe level, are never suppressed by the verbosity threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Notice,
+    Info,
+    Debug,
+}
+
+static VERBOSITY: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Sets the verbosity threshold.  `warn!`, `notice!`, `info!`, `debug!`,
+/// and their path and error variants are suppressed when their level is
+/// less severe than `level`.  The default is `Level::Warn`.
+pub fn set_verbosity(level: Level) {
+    VERBOSITY.store(level as usize + 1, Ordering::Relaxed);
+}
+
+fn verbosity() -> Level {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        1 => Level::Error,
+        2 => Level::Warn,
+        3 => Level::Notice,
+        4 => Level::Info,
+        5 => Level::Debug,
+        _ => Level::Warn,
+    }
+}
+
+struct Sink {
+    writer: Mutex<Box<Write + Send>>,
+}
+
+static mut SINK: *const Sink = 0 as *const Sink;
+static SINK_ONCE: Once = ONCE_INIT;
+
+fn init_sink() {
+    SINK_ONCE.call_once(|| {
+        let instance = Sink { writer: Mutex::new(tester::default_output()) };
+        unsafe {
+            SINK = mem::transmute(Box::new(instance));
+        }
+    });
+}
+
+/// Sets the destination that `err!`, `warn!`, and their variants write
+/// diagnostic messages to.  The default is the standard error output.
+/// This lets an embedder (a shell, a test harness, a GUI wrapper) capture
+/// or redirect all diagnostics in-process instead of letting them go to
+/// the process's fd 2.
+pub fn set_output(output: Box<Write + Send>) {
+    init_sink();
+    let sink = unsafe { &(*SINK).writer };
+    *sink.lock().unwrap() = output;
+}
+
+/// Writes the whole buffer while holding the sink's lock, so that
+/// diagnostics from different threads never interleave mid-line.
+fn write_output(buf: &[u8]) -> io::Result<()> {
+    init_sink();
+    let sink = unsafe { &(*SINK).writer };
+    sink.lock().unwrap().write_all(buf)
+}
+
 /// Prints the formatted message to the standard error output (stderr)
 /// and terminates the program with the given `status` value.
 /// The program name, a colon, and a space are output before the message,
@@ -43,11 +163,11 @@ use progname;
 macro_rules! err {
     ($status:expr, $fmt:expr) => (
         $crate::err::verrp($status, None as Option<&str>,
-                           format_args!(concat!($fmt, "\n")));
+                           format_args!(concat!($fmt, "\n")), None);
     );
     ($status:expr, $fmt:expr, $($args:tt)*) => (
         $crate::err::verrp($status, None as Option<&str>,
-                           format_args!(concat!($fmt, "\n"), $($args)*));
+                           format_args!(concat!($fmt, "\n"), $($args)*), None);
     );
 }
 
@@ -59,11 +179,61 @@ macro_rules! err {
 macro_rules! errp {
     ($status:expr, $path:expr, $fmt:expr) => (
         $crate::err::verrp($status, Some($path),
-                           format_args!(concat!($fmt, "\n")));
+                           format_args!(concat!($fmt, "\n")), None);
     );
     ($status:expr, $path:expr, $fmt:expr, $($args:tt)*) => (
         $crate::err::verrp($status, Some($path),
-                           format_args!(concat!($fmt, "\n"), $($args)*));
+                           format_args!(concat!($fmt, "\n"), $($args)*), None);
+    );
+}
+
+/// Prints the formatted message, followed by the given error, to the
+/// standard error output (stderr), and terminates the program with the
+/// given `status` value.  This is the `errc!`/`errpc!`/`warnc!`/`warnpc!`
+/// family's faithful counterpart of BSD `err(3)`/`warn(3)`: where `err!`
+/// and `warn!` only ever print the message (as `errx(3)`/`warnx(3)` do),
+/// these also append `: ` and the `Display` of an error value after it.
+/// If the error is omitted, `io::Error::last_os_error()` is used and no
+/// message is printed, mirroring `err(3)` called with a `NULL` format.
+#[macro_export]
+macro_rules! errc {
+    ($status:expr) => (
+        $crate::err::verrp($status, None as Option<&str>, format_args!(""),
+                           Some(&::std::io::Error::last_os_error() as &::std::fmt::Display));
+    );
+    ($status:expr, $err:expr) => (
+        $crate::err::verrp($status, None as Option<&str>, format_args!(""),
+                           Some(&$err as &::std::fmt::Display));
+    );
+    ($status:expr, $err:expr, $fmt:expr) => (
+        $crate::err::verrp($status, None as Option<&str>, format_args!($fmt),
+                           Some(&$err as &::std::fmt::Display));
+    );
+    ($status:expr, $err:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::verrp($status, None as Option<&str>,
+                           format_args!($fmt, $($args)*), Some(&$err as &::std::fmt::Display));
+    );
+}
+
+/// Like `errc!`, but also takes a pathname, printed (with surrounding
+/// colons) between the program name and the message, just like `errp!`.
+#[macro_export]
+macro_rules! errpc {
+    ($status:expr, $path:expr) => (
+        $crate::err::verrp($status, Some($path), format_args!(""),
+                           Some(&::std::io::Error::last_os_error() as &::std::fmt::Display));
+    );
+    ($status:expr, $path:expr, $err:expr) => (
+        $crate::err::verrp($status, Some($path), format_args!(""),
+                           Some(&$err as &::std::fmt::Display));
+    );
+    ($status:expr, $path:expr, $err:expr, $fmt:expr) => (
+        $crate::err::verrp($status, Some($path), format_args!($fmt),
+                           Some(&$err as &::std::fmt::Display));
+    );
+    ($status:expr, $path:expr, $err:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::verrp($status, Some($path),
+                           format_args!($fmt, $($args)*), Some(&$err as &::std::fmt::Display));
     );
 }
 
@@ -74,11 +244,11 @@ macro_rules! errp {
 macro_rules! warn {
     ($fmt:expr) => (
         $crate::err::vwarnp(None as Option<&str>,
-                            format_args!(concat!($fmt, "\n")));
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Warn);
     );
     ($fmt:expr, $($args:tt)*) => (
         $crate::err::vwarnp(None as Option<&str>,
-                            format_args!(concat!($fmt, "\n"), $($args)*));
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Warn);
     );
 }
 
@@ -89,25 +259,176 @@ macro_rules! warn {
 macro_rules! warnp {
     ($path:expr, $fmt:expr) => (
         $crate::err::vwarnp(Some($path),
-                            format_args!(concat!($fmt, "\n")));
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Warn);
     );
     ($path:expr, $fmt:expr, $($args:tt)*) => (
         $crate::err::vwarnp(Some($path),
-                            format_args!(concat!($fmt, "\n"), $($args)*));
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Warn);
+    );
+}
+
+/// Non-fatal counterpart of `errc!`: prints the formatted message,
+/// followed by the given (or automatically obtained) error, to the
+/// standard error output (stderr), without terminating the program.
+#[macro_export]
+macro_rules! warnc {
+    () => (
+        $crate::err::vwarnp(None as Option<&str>, format_args!(""),
+                            Some(&::std::io::Error::last_os_error() as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+    ($err:expr) => (
+        $crate::err::vwarnp(None as Option<&str>, format_args!(""),
+                            Some(&$err as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+    ($err:expr, $fmt:expr) => (
+        $crate::err::vwarnp(None as Option<&str>, format_args!($fmt),
+                            Some(&$err as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+    ($err:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!($fmt, $($args)*), Some(&$err as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+}
+
+/// Like `warnc!`, but also takes a pathname, printed (with surrounding
+/// colons) between the program name and the message, just like `warnp!`.
+#[macro_export]
+macro_rules! warnpc {
+    ($path:expr) => (
+        $crate::err::vwarnp(Some($path), format_args!(""),
+                            Some(&::std::io::Error::last_os_error() as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+    ($path:expr, $err:expr) => (
+        $crate::err::vwarnp(Some($path), format_args!(""),
+                            Some(&$err as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+    ($path:expr, $err:expr, $fmt:expr) => (
+        $crate::err::vwarnp(Some($path), format_args!($fmt),
+                            Some(&$err as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+    ($path:expr, $err:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!($fmt, $($args)*), Some(&$err as &::std::fmt::Display), false, $crate::err::Level::Warn);
+    );
+}
+
+/// Prints the formatted message to the standard error output (stderr),
+/// unless the current verbosity threshold (see `set_verbosity`)
+/// suppresses `Level::Notice`.  The program name, a colon, and a space
+/// are output before the message, and a newline character follows.
+#[macro_export]
+macro_rules! notice {
+    ($fmt:expr) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Notice);
+    );
+    ($fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Notice);
+    );
+}
+
+/// Like `notice!`, but also takes a pathname, printed (with surrounding
+/// colons) between the program name and the message, just like `warnp!`.
+#[macro_export]
+macro_rules! noticep {
+    ($path:expr, $fmt:expr) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Notice);
+    );
+    ($path:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Notice);
+    );
+}
+
+/// Prints the formatted message to the standard error output (stderr),
+/// unless the current verbosity threshold (see `set_verbosity`)
+/// suppresses `Level::Info`.  The program name, a colon, and a space
+/// are output before the message, and a newline character follows.
+#[macro_export]
+macro_rules! info {
+    ($fmt:expr) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Info);
+    );
+    ($fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Info);
+    );
+}
+
+/// Like `info!`, but also takes a pathname, printed (with surrounding
+/// colons) between the program name and the message, just like `warnp!`.
+#[macro_export]
+macro_rules! infop {
+    ($path:expr, $fmt:expr) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Info);
+    );
+    ($path:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Info);
+    );
+}
+
+/// Prints the formatted message to the standard error output (stderr),
+/// unless the current verbosity threshold (see `set_verbosity`)
+/// suppresses `Level::Debug`.  The program name, a colon, and a space
+/// are output before the message, and a newline character follows.
+#[macro_export]
+macro_rules! debug {
+    ($fmt:expr) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Debug);
+    );
+    ($fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(None as Option<&str>,
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Debug);
+    );
+}
+
+/// Like `debug!`, but also takes a pathname, printed (with surrounding
+/// colons) between the program name and the message, just like `warnp!`.
+#[macro_export]
+macro_rules! debugp {
+    ($path:expr, $fmt:expr) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!(concat!($fmt, "\n")), None, false, $crate::err::Level::Debug);
+    );
+    ($path:expr, $fmt:expr, $($args:tt)*) => (
+        $crate::err::vwarnp(Some($path),
+                            format_args!(concat!($fmt, "\n"), $($args)*), None, false, $crate::err::Level::Debug);
     );
 }
 
 /// This function is not a part of public/stable APIs.
-/// This function should be used through the `err!` or `errp!` macros.
-pub fn verrp<P>(status: i32, path: Option<P>, fmt: fmt::Arguments) -> ! where P: AsRef<Path> {
-    vwarnp(path, fmt);
+/// This function should be used through the `err!`, `errp!`, `errc!`,
+/// or `errpc!` macros.
+pub fn verrp<P>(status: i32, path: Option<P>, fmt: fmt::Arguments,
+                 err: Option<&::std::fmt::Display>) -> ! where P: AsRef<Path> {
+    vwarnp(path, fmt, err, true, Level::Error);
     tester::exit(status);
 }
 
 /// This function is not a part of public/stable APIs.
-/// This function should be used through the `warn!` or `warnp!` macros.
-pub fn vwarnp<P>(path: Option<P>, fmt: fmt::Arguments) where P: AsRef<Path> {
+/// This function should be used through the `warn!`, `warnp!`, `warnc!`,
+/// `warnpc!`, `notice!`, `info!`, `debug!`, and their path variants.
+/// `fatal` requests the `error:` marker that distinguishes messages
+/// routed through `err!`/`errp!`/`errc!`/`errpc!`; it has no effect
+/// unless color is in use (see `set_color`).  `level` is compared
+/// against the verbosity threshold (see `set_verbosity`); the message
+/// is dropped without being composed if it is less severe.
+pub fn vwarnp<P>(path: Option<P>, fmt: fmt::Arguments, err: Option<&::std::fmt::Display>,
+                  fatal: bool, level: Level) where P: AsRef<Path> {
+    if level > verbosity() {
+        return;
+    }
     let mut buf = Vec::new();
+    let color = use_color();
+    if color {
+        buf.extend_from_slice(b"\x1b[1m");
+    }
     if let Some(ref os) = *progname::getprogname_arc() {
         #[cfg(unix)]
         buf.extend_from_slice(os.as_bytes());
@@ -117,7 +438,13 @@ pub fn vwarnp<P>(path: Option<P>, fmt: fmt::Arguments) where P: AsRef<Path> {
             None => {},
         };
     }
+    if color {
+        buf.extend_from_slice(b"\x1b[0m");
+    }
     buf.extend_from_slice(b": ");
+    if fatal && color {
+        buf.extend_from_slice(b"\x1b[31merror:\x1b[0m ");
+    }
     if let Some(path) = path {
         #[cfg(unix)]
         buf.extend_from_slice(path.as_ref().as_os_str().as_bytes());
@@ -130,7 +457,14 @@ pub fn vwarnp<P>(path: Option<P>, fmt: fmt::Arguments) where P: AsRef<Path> {
     }
     let msgstart = buf.len();
     let _ = buf.write_fmt(fmt);
-    if let Err(e) = tester::stderr().write(&buf) {
+    if let Some(err) = err {
+        if buf.len() > msgstart {
+            buf.extend_from_slice(b": ");
+        }
+        let _ = write!(&mut buf, "{}", err);
+        buf.extend_from_slice(b"\n");
+    }
+    if let Err(e) = write_output(&buf) {
         // The message was composed by write_fmt, so from_utf8 should not fail.
         let msg = str::from_utf8(&buf[msgstart..]).unwrap_or("");
         // If writing to stderr failed, writing the panic message will
@@ -141,16 +475,19 @@ pub fn vwarnp<P>(path: Option<P>, fmt: fmt::Arguments) where P: AsRef<Path> {
 
 #[cfg(not(test))]
 mod tester {
+    use std::io::Write;
+
     #[inline(always)]
     pub fn exit(status: i32) -> ! { ::std::process::exit(status); }
-    #[inline(always)]
-    pub fn stderr() -> ::std::io::Stderr { ::std::io::stderr() }
+    pub fn default_output() -> Box<Write + Send> { Box::new(::std::io::stderr()) }
 }
 
 #[cfg(test)]
 mod tester {
+    use std::io::Write;
+
     pub fn exit(status: i32) -> ! { panic!("expected exit with {}", status); }
-    pub fn stderr() -> DummyStderr { DummyStderr::new() }
+    pub fn default_output() -> Box<Write + Send> { Box::new(DummyStderr::new()) }
 
     use std::cell::RefCell;
     use std::io;
@@ -177,8 +514,25 @@ mod tester {
 #[cfg(test)]
 mod tests {
     use std::ffi::OsStr;
+    use std::io;
+    use std::str;
+    use std::sync::Arc;
+    use std::thread;
     use super::*;
 
+    // Besides capturing into `buf`, also forwards to the regular
+    // thread-local dummy stderr, so tests running concurrently on other
+    // threads are unaffected by the sink being swapped out for the
+    // duration of a `set_output` test.
+    struct TeeSink(Arc<Mutex<Vec<u8>>>);
+    impl io::Write for TeeSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            tester::DummyStderr::new().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
     // The status 0 is a bit dangerous.  The test runner assumes the
     // tests are successful if the exit status is 0, even when the
     // dummy exit is not working and the process really exits before
@@ -202,6 +556,53 @@ mod tests {
     }
 
     #[test]
+    #[should_panic(expected = "expected exit with 0")]
+    fn errc1() {
+        errc!(0, io::Error::from_raw_os_error(2), "can't open");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected exit with 0")]
+    fn errc0() {
+        // The zero-arg form reads io::Error::last_os_error() automatically.
+        let _ = ::std::fs::File::open("/nonexistent-path-for-unixcli-tests");
+        errc!(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected exit with 0")]
+    fn errpc1() {
+        errpc!(0, Path::new("Path"), io::Error::from_raw_os_error(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected exit with 0")]
+    fn errpc0() {
+        let _ = ::std::fs::File::open("/nonexistent-path-for-unixcli-tests");
+        errpc!(0, Path::new("Path"));
+    }
+
+    // warn, warnp, warnc, warnpc, warn_named_param, color, notice_info_debug,
+    // verbosity, and set_output_basic/set_output_threads all read back
+    // captured output (via `tester::get_stderr()` or a sink installed with
+    // `set_output`), and some of them mutate process-global state (`COLOR`,
+    // `VERBOSITY`, the sink).  Run concurrently, they can observe each
+    // other's output or clobber each other's sink, so they are driven from
+    // this single #[test] instead, like `progname::tests::all`.
+    #[test]
+    fn globals() {
+        warn();
+        warnp();
+        warnc();
+        warnpc();
+        warn_named_param();
+        color();
+        notice_info_debug();
+        verbosity();
+        set_output_basic();
+        set_output_threads();
+    }
+
     fn warn() {
         warn!("warn 1");
         assert!(tester::get_stderr().ends_with(b": warn 1\n"));
@@ -211,7 +612,6 @@ mod tests {
         assert!(tester::get_stderr().ends_with(b": warn 3\n"));
     }
 
-    #[test]
     fn warnp() {
         warnp!("str", "warnp 1");
         assert!(tester::get_stderr().ends_with(b": str: warnp 1\n"));
@@ -225,15 +625,136 @@ mod tests {
         assert!(tester::get_stderr().ends_with(b": OsStr: warnp 1\n"));
     }
 
+    fn warnc() {
+        // The zero-arg form reads io::Error::last_os_error() automatically.
+        let _ = ::std::fs::File::open("/nonexistent-path-for-unixcli-tests");
+        warnc!();
+        assert!(tester::get_stderr().ends_with(b": No such file or directory (os error 2)\n"));
+        warnc!(io::Error::from_raw_os_error(2));
+        assert!(tester::get_stderr().ends_with(b": No such file or directory (os error 2)\n"));
+        warnc!(io::Error::from_raw_os_error(2), "can't open");
+        assert!(tester::get_stderr().ends_with(b": can't open: No such file or directory (os error 2)\n"));
+        warnc!(io::Error::from_raw_os_error(2), "can't open {}", "it");
+        assert!(tester::get_stderr().ends_with(b": can't open it: No such file or directory (os error 2)\n"));
+    }
+
+    fn warnpc() {
+        let _ = ::std::fs::File::open("/nonexistent-path-for-unixcli-tests");
+        warnpc!("file");
+        assert!(tester::get_stderr().ends_with(b": file: No such file or directory (os error 2)\n"));
+        warnpc!("file", io::Error::from_raw_os_error(2));
+        assert!(tester::get_stderr().ends_with(b": file: No such file or directory (os error 2)\n"));
+        warnpc!("file", io::Error::from_raw_os_error(2), "can't open");
+        assert!(tester::get_stderr().ends_with(b": file: can't open: No such file or directory (os error 2)\n"));
+    }
+
     #[test]
     #[should_panic(expected = "expected exit with 1")]
     fn err_named_param() {
         err!(1, "x = {x}, y = {y}", y = 20, x = 10);
     }
 
-    #[test]
     fn warn_named_param() {
         warn!("x = {x}, y = {y}", y = 20, x = 10);
         assert!(tester::get_stderr().ends_with(b": x = 10, y = 20\n"));
     }
+
+    fn color() {
+        set_color(ColorChoice::Never);
+        let before = tester::get_stderr().len();
+        warn!("plain");
+        assert!(tester::get_stderr().ends_with(b": plain\n"));
+        assert!(!tester::get_stderr()[before..].contains(&0x1b));
+
+        set_color(ColorChoice::Always);
+        warn!("bold name");
+        assert!(tester::get_stderr().ends_with(b"\x1b[0m: bold name\n"));
+
+        vwarnp(None as Option<&str>, format_args!("oops\n"), None, true, Level::Error);
+        assert!(tester::get_stderr().ends_with(b"\x1b[0m: \x1b[31merror:\x1b[0m oops\n"));
+
+        set_color(ColorChoice::Auto);
+    }
+
+    fn notice_info_debug() {
+        // Notice/Info/Debug are below the default (Warn) threshold, so
+        // raise it for the duration of this test.
+        set_verbosity(Level::Debug);
+        notice!("notice 1");
+        assert!(tester::get_stderr().ends_with(b": notice 1\n"));
+        noticep!("file", "notice {}", 2);
+        assert!(tester::get_stderr().ends_with(b": file: notice 2\n"));
+        info!("info 1");
+        assert!(tester::get_stderr().ends_with(b": info 1\n"));
+        infop!("file", "info {}", 2);
+        assert!(tester::get_stderr().ends_with(b": file: info 2\n"));
+        debug!("debug 1");
+        assert!(tester::get_stderr().ends_with(b": debug 1\n"));
+        debugp!("file", "debug {}", 2);
+        assert!(tester::get_stderr().ends_with(b": file: debug 2\n"));
+        set_verbosity(Level::Warn);
+    }
+
+    fn verbosity() {
+        // The default threshold lets warn! through but not notice!/info!/debug!.
+        let before = tester::get_stderr().len();
+        notice!("hidden");
+        assert_eq!(tester::get_stderr().len(), before);
+        warn!("shown");
+        assert!(tester::get_stderr().ends_with(b": shown\n"));
+
+        set_verbosity(Level::Notice);
+        notice!("notice shown");
+        assert!(tester::get_stderr().ends_with(b": notice shown\n"));
+        let before = tester::get_stderr().len();
+        info!("still hidden");
+        assert_eq!(tester::get_stderr().len(), before);
+
+        set_verbosity(Level::Debug);
+        debug!("debug shown");
+        assert!(tester::get_stderr().ends_with(b": debug shown\n"));
+
+        set_verbosity(Level::Warn);
+    }
+
+    fn set_output_basic() {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        set_output(Box::new(TeeSink(buf.clone())));
+        warn!("captured");
+        assert!(buf.lock().unwrap().ends_with(b": captured\n"));
+        set_output(tester::default_output());
+    }
+
+    fn set_output_threads() {
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        set_output(Box::new(TeeSink(buf.clone())));
+
+        let children: Vec<_> = (0..4).map(|t| {
+            thread::spawn(move || {
+                for i in 0..500 {
+                    warn!("thread {} line {}", t, i);
+                }
+            })
+        }).collect();
+        for child in children {
+            child.join().unwrap();
+        }
+
+        set_output(tester::default_output());
+
+        // Every line must be a complete, unmangled message; if the sink's
+        // lock didn't cover the whole write, two threads' writes could
+        // interleave mid-line and produce garbage here.
+        let captured = buf.lock().unwrap();
+        let text = str::from_utf8(&captured).unwrap();
+        for line in text.lines() {
+            let rest = line.splitn(2, ": ").nth(1).unwrap();
+            let mut words = rest.split_whitespace();
+            assert_eq!(words.next(), Some("thread"));
+            words.next().unwrap().parse::<u32>().unwrap();
+            assert_eq!(words.next(), Some("line"));
+            words.next().unwrap().parse::<u32>().unwrap();
+            assert_eq!(words.next(), None);
+        }
+    }
 }
@@ -0,0 +1,72 @@
+//
+// Copyright (c) 2017 KAMADA Ken'ichi.
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions
+// are met:
+// 1. Redistributions of source code must retain the above copyright
+//    notice, this list of conditions and the following disclaimer.
+// 2. Redistributions in binary form must reproduce the above copyright
+//    notice, this list of conditions and the following disclaimer in the
+//    documentation and/or other materials provided with the distribution.
+//
+// THIS SOFTWARE IS PROVIDED BY THE AUTHOR AND CONTRIBUTORS ``AS IS'' AND
+// ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE
+// ARE DISCLAIMED.  IN NO EVENT SHALL THE AUTHOR OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS
+// OR SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION)
+// HOWEVER CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT
+// LIABILITY, OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY
+// OUT OF THE USE OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF
+// SUCH DAMAGE.
+//
+
+//! I/O helpers.
+
+use std::io;
+use std::io::{Read, Write};
+
+/// Copies all bytes from `reader` to `writer`, letting the standard
+/// library pick the fastest available path.
+///
+/// This is a thin wrapper around `std::io::copy`, which on Linux
+/// dispatches to `copy_file_range`, `sendfile`, or `splice` when both
+/// ends are file- or pipe-backed descriptors, and only falls back to a
+/// userspace read/write loop otherwise.  Passing the file (or
+/// `io::stdin()`/`io::stdout()`) directly, rather than through a
+/// `BufReader`, lets the kernel fast path engage; it also works when
+/// either side is a pipe, since `io::copy` picks `splice` or the
+/// buffered loop as appropriate.
+pub fn fastcopy<R, W>(reader: &mut R, writer: &mut W) -> io::Result<u64>
+    where R: Read, W: Write
+{
+    io::copy(reader, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let data = b"the quick brown fox jumps over the lazy dog".to_vec();
+        let mut reader = Cursor::new(data.clone());
+        let mut writer = Vec::new();
+        let n = fastcopy(&mut reader, &mut writer).unwrap();
+        assert_eq!(n, data.len() as u64);
+        assert_eq!(writer, data);
+    }
+
+    #[test]
+    fn empty() {
+        let mut reader = Cursor::new(Vec::new());
+        let mut writer = Vec::new();
+        let n = fastcopy(&mut reader, &mut writer).unwrap();
+        assert_eq!(n, 0);
+        assert!(writer.is_empty());
+    }
+}